@@ -0,0 +1,130 @@
+use crate::http::HttpRequest;
+use crate::pool::Pool;
+use postgres::Error as PostgresError;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+
+const SESSION_TTL: &str = "1 hour";
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+pub struct Session {
+    pub user_id: i32,
+    pub token: String,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidCredentials,
+    Backend(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidCredentials => write!(f, "invalid credentials"),
+            AuthError::Backend(msg) => write!(f, "backend error: {}", msg),
+        }
+    }
+}
+
+impl From<PostgresError> for AuthError {
+    fn from(err: PostgresError) -> Self {
+        AuthError::Backend(err.to_string())
+    }
+}
+
+/// Hashes a password with a per-user salt. Not constant-time; good enough
+/// for this project's threat model but swap for a proper KDF (argon2,
+/// bcrypt) before handling real user data.
+pub fn hash_password(password: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(password.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn generate_salt() -> String {
+    random_hex(16)
+}
+
+fn generate_token() -> String {
+    random_hex(32)
+}
+
+fn random_hex(num_bytes: usize) -> String {
+    let mut bytes = vec![0u8; num_bytes];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Login + bearer-session bookkeeping, kept separate from `Storage` since it
+/// owns its own tables (`sessions`) and isn't part of the user CRUD surface.
+pub struct AuthService {
+    pool: Arc<Pool>,
+}
+
+impl AuthService {
+    pub fn new(pool: Arc<Pool>) -> Self {
+        AuthService { pool }
+    }
+
+    pub fn login(&self, email: &str, password: &str) -> Result<String, AuthError> {
+        let mut client = self.pool.get()?;
+        let row = client
+            .query_opt(
+                "SELECT id, password_hash, salt FROM users WHERE email = $1",
+                &[&email],
+            )?
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        let user_id: i32 = row.get(0);
+        let password_hash: String = row.get(1);
+        let salt: String = row.get(2);
+
+        if hash_password(password, &salt) != password_hash {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        let token = generate_token();
+        client.execute(
+            &format!(
+                "INSERT INTO sessions (token, user_id, expires_at) VALUES ($1, $2, NOW() + INTERVAL '{}')",
+                SESSION_TTL
+            ),
+            &[&token, &user_id],
+        )?;
+
+        Ok(token)
+    }
+
+    fn session_for_token(&self, token: &str) -> Result<Option<Session>, AuthError> {
+        let mut client = self.pool.get()?;
+        let row = client.query_opt(
+            "SELECT user_id FROM sessions WHERE token = $1 AND expires_at > NOW()",
+            &[&token],
+        )?;
+        Ok(row.map(|row| Session {
+            user_id: row.get(0),
+            token: token.to_string(),
+        }))
+    }
+}
+
+/// Pulls the bearer token out of the `Authorization` header and looks it up.
+/// Returns `None` if the header is missing or the token is unknown/expired.
+pub fn check_auth(auth: &AuthService, request: &HttpRequest) -> Option<Session> {
+    let token = extract_bearer_token(request)?;
+    auth.session_for_token(token).ok().flatten()
+}
+
+fn extract_bearer_token(request: &HttpRequest) -> Option<&str> {
+    request.headers.get("authorization")?.strip_prefix("Bearer ")
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::net::TcpStream;
+
+const READ_CHUNK: usize = 1024;
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+/// A parsed HTTP request: request line broken into `method`/`path`, headers
+/// lower-cased by name, and a body read for exactly `Content-Length` bytes
+/// (instead of whatever happened to fit in the first read).
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// Reads `stream` until the header terminator is found, then reads exactly
+/// `Content-Length` body bytes. Unlike a single fixed-size `read`, this
+/// handles requests whose headers+body span more than one TCP read.
+pub fn parse_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; READ_CHUNK];
+
+    let header_end = loop {
+        if let Some(pos) = find_header_terminator(&buf) {
+            break pos;
+        }
+        if buf.len() > MAX_HEADER_BYTES {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "headers too large"));
+        }
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before headers completed",
+            ));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let header_text = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = header_text.split("\r\n");
+
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(HttpRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn find_header_terminator(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
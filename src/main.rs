@@ -1,34 +1,61 @@
-use serde::{Serialize, Deserialize};
-use postgres::{Client, NoTls, Error as PostgresError};
+use postgres::Error as PostgresError;
 use std::env;
 use std::net::{TcpListener, TcpStream};
-use std::io::{Read, Write};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
 use serde_json;
 
-#[derive(Serialize, Deserialize)]
-struct User {
-    id: Option<i32>,
-    name: String,
-    email: String,
-}
+mod auth;
+mod http;
+mod metrics;
+mod pool;
+mod storage;
+mod tls;
+use auth::{check_auth, AuthError, AuthService, LoginRequest};
+use http::HttpRequest;
+use metrics::Metrics;
+use pool::Pool;
+use storage::{PostgresStorage, Storage, StorageError, User};
 
 const OK_RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n";
+const CREATED: &str = "HTTP/1.1 201 CREATED\r\nContent-Type: application/json\r\n\r\n";
+const METRICS_RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\r\n";
 const NOT_FOUND: &str = "HTTP/1.1 404 NOT FOUND\r\n\r\n";
+const CONFLICT: &str = "HTTP/1.1 409 CONFLICT\r\n\r\n";
+const UNAUTHORIZED: &str = "HTTP/1.1 401 UNAUTHORIZED\r\n\r\n";
 const INTERNAL_ERROR: &str = "HTTP/1.1 500 INTERNAL ERROR\r\n\r\n";
 
+/// Shared services handed to every connection; grows as new subsystems
+/// (storage, auth, ...) are added.
+struct AppState {
+    storage: Arc<dyn Storage>,
+    auth: AuthService,
+    pool: Arc<Pool>,
+    metrics: Metrics,
+}
+
 fn main() -> Result<(), PostgresError> {
     // Читаем DATABASE_URL из окружения во время выполнения
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     set_database(&db_url)?;
 
+    let pool = Arc::new(Pool::new(&db_url)?);
+    let state = Arc::new(AppState {
+        storage: Arc::new(PostgresStorage::new(Arc::clone(&pool))),
+        auth: AuthService::new(Arc::clone(&pool)),
+        pool: Arc::clone(&pool),
+        metrics: Metrics::new(),
+    });
+
     let listener = TcpListener::bind("0.0.0.0:8080").unwrap();
     println!("Server listening on port 8080");
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
-                handle_client(stream, &db_url);
+                handle_client(stream, &state);
             }
             Err(e) => eprintln!("Unable to accept connection: {}", e),
         }
@@ -36,166 +63,207 @@ fn main() -> Result<(), PostgresError> {
     Ok(())
 }
 
-fn handle_client(mut stream: TcpStream, db_url: &str) {
-    let mut buffer = [0; 1024];
-    let mut request = String::new();
+fn handle_client(mut stream: TcpStream, state: &AppState) {
+    let request = match http::parse_request(&mut stream) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Unable to read stream: {}", e);
+            return;
+        }
+    };
+
+    let route = route_label(&request.method, &request.path);
+    let started_at = Instant::now();
+
+    let storage = &*state.storage;
+    let (status_line, content) = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => handle_metrics_request(state),
+        ("POST", "/login") => handle_login_request(&request, &state.auth),
+        ("POST", "/users/batch") => match require_auth(&state.auth, &request) {
+            Ok(()) => handle_post_batch_request(&request, storage),
+            Err(resp) => resp,
+        },
+        // Registration is deliberately open: it's the only way to create the
+        // first account, and gating it behind require_auth would make the
+        // whole auth system unreachable on a fresh database.
+        ("POST", path) if path.starts_with("/users") => handle_post_request(&request, storage),
+        ("GET", path) if path.starts_with("/users/") => handle_get_request(&request, storage),
+        ("GET", path) if path.starts_with("/users") => handle_get_all_request(&request, storage),
+        ("PUT", path) if path.starts_with("/users/") => match require_auth(&state.auth, &request) {
+            Ok(()) => handle_put_request(&request, storage),
+            Err(resp) => resp,
+        },
+        ("DELETE", path) if path.starts_with("/users/") => match require_auth(&state.auth, &request) {
+            Ok(()) => handle_delete_request(&request, storage),
+            Err(resp) => resp,
+        },
+        _ => (NOT_FOUND.to_string(), "404 Not Found".to_string()),
+    };
+
+    state.metrics.record(route, &status_line, started_at.elapsed());
+
+    let response = format!("{}{}", status_line, content);
+    if let Err(e) = stream.write_all(response.as_bytes()) {
+        eprintln!("Failed to write response: {}", e);
+    }
+}
 
-    match stream.read(&mut buffer) {
-        Ok(size) if size > 0 => {
-            request.push_str(&String::from_utf8_lossy(&buffer[..size]));
+fn route_label(method: &str, path: &str) -> &'static str {
+    match (method, path) {
+        ("GET", "/metrics") => "metrics",
+        ("POST", "/login") => "login",
+        ("POST", "/users/batch") => "users_batch",
+        ("POST", p) if p.starts_with("/users") => "users_create",
+        ("GET", p) if p.starts_with("/users/") => "users_get",
+        ("GET", p) if p.starts_with("/users") => "users_list",
+        ("PUT", p) if p.starts_with("/users/") => "users_update",
+        ("DELETE", p) if p.starts_with("/users/") => "users_delete",
+        _ => "not_found",
+    }
+}
 
-            let (status_line, content) = match &*request {
-                r if r.starts_with("POST /users") => handle_post_request(r, db_url),
-                r if r.starts_with("GET /users/") => handle_get_request(r, db_url),
-                r if r.starts_with("GET /users") => handle_get_all_request(r, db_url),
-                r if r.starts_with("PUT /users/") => handle_put_request(r, db_url),
-                r if r.starts_with("DELETE /users/") => handle_delete_request(r, db_url),
-                _ => (NOT_FOUND.to_string(), "404 Not Found".to_string()),
-            };
+fn handle_metrics_request(state: &AppState) -> (String, String) {
+    let body = state.metrics.render(state.pool.in_use());
+    (METRICS_RESPONSE.to_string(), body)
+}
 
-            let response = format!("{}{}", status_line, content);
-            if let Err(e) = stream.write_all(response.as_bytes()) {
-                eprintln!("Failed to write response: {}", e);
+fn handle_login_request(request: &HttpRequest, auth: &AuthService) -> (String, String) {
+    match get_login_request_body(request) {
+        Ok(creds) => match auth.login(&creds.email, &creds.password) {
+            Ok(token) => (
+                OK_RESPONSE.to_string(),
+                serde_json::json!({ "token": token }).to_string(),
+            ),
+            Err(AuthError::InvalidCredentials) => {
+                (UNAUTHORIZED.to_string(), "Invalid credentials".to_string())
             }
-        }
-        Ok(_) => eprintln!("Received empty request"),
-        Err(e) => eprintln!("Unable to read stream: {}", e),
+            Err(AuthError::Backend(msg)) => (INTERNAL_ERROR.to_string(), msg),
+        },
+        Err(_) => (INTERNAL_ERROR.to_string(), "Invalid JSON".to_string()),
     }
 }
 
-fn handle_post_request(request: &str, db_url: &str) -> (String, String) {
+/// Gate for the mutating endpoints: `Ok(())` if the request carries a valid
+/// bearer token, `Err(response)` with a ready-to-send 401 otherwise.
+fn require_auth(auth: &AuthService, request: &HttpRequest) -> Result<(), (String, String)> {
+    match check_auth(auth, request) {
+        Some(_) => Ok(()),
+        None => Err((UNAUTHORIZED.to_string(), "Unauthorized".to_string())),
+    }
+}
+
+fn handle_post_request(request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
     match get_user_request_body(request) {
-        Ok(user) => {
-            match Client::connect(db_url, NoTls) {
-                Ok(mut client) => {
-                    let res = client.execute(
-                        "INSERT INTO users (name, email) VALUES ($1, $2)",
-                        &[&user.name, &user.email],
-                    );
-                    match res {
-                        Ok(_) => (OK_RESPONSE.to_string(), "User created".to_string()),
-                        Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-                    }
-                }
-                Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-            }
-        }
+        Ok(user) => match storage.create_user(&user) {
+            Ok(_) => (OK_RESPONSE.to_string(), "User created".to_string()),
+            Err(err) => map_storage_error(err),
+        },
+        Err(_) => (INTERNAL_ERROR.to_string(), "Invalid JSON".to_string()),
+    }
+}
+
+fn handle_post_batch_request(request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
+    match get_users_request_body(request) {
+        Ok(users) => match storage.create_users_batch(&users) {
+            Ok(ids) => (CREATED.to_string(), serde_json::to_string(&ids).unwrap()),
+            Err(err) => map_storage_error(err),
+        },
         Err(_) => (INTERNAL_ERROR.to_string(), "Invalid JSON".to_string()),
     }
 }
 
-fn handle_get_request(request: &str, db_url: &str) -> (String, String) {
-    let id_opt = get_id(request).parse::<i32>().ok();
+fn handle_get_request(request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
+    let id_opt = get_id(&request.path).parse::<i32>().ok();
     if id_opt.is_none() {
         return (NOT_FOUND.to_string(), "Invalid ID".to_string());
     }
     let id = id_opt.unwrap();
 
-    match Client::connect(db_url, NoTls) {
-        Ok(mut client) => {
-            let row = client.query_opt("SELECT id, name, email FROM users WHERE id = $1", &[&id]);
-            match row {
-                Ok(Some(row)) => {
-                    let user = User {
-                        id: row.get(0),
-                        name: row.get(1),
-                        email: row.get(2),
-                    };
-                    (OK_RESPONSE.to_string(), serde_json::to_string(&user).unwrap())
-                }
-                Ok(None) => (NOT_FOUND.to_string(), "User not found".to_string()),
-                Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-            }
-        }
-        Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
+    match storage.get_user(id) {
+        Ok(user) => (OK_RESPONSE.to_string(), serde_json::to_string(&user).unwrap()),
+        Err(err) => map_storage_error(err),
     }
 }
 
-fn handle_get_all_request(_request: &str, db_url: &str) -> (String, String) {
-    match Client::connect(db_url, NoTls) {
-        Ok(mut client) => {
-            let rows = client.query("SELECT id, name, email FROM users", &[]).unwrap_or_default();
-            let users: Vec<User> = rows
-                .iter()
-                .map(|row| User {
-                    id: row.get(0),
-                    name: row.get(1),
-                    email: row.get(2),
-                })
-                .collect();
-            (OK_RESPONSE.to_string(), serde_json::to_string(&users).unwrap())
-        }
-        Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
+fn handle_get_all_request(_request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
+    match storage.list_users() {
+        Ok(users) => (OK_RESPONSE.to_string(), serde_json::to_string(&users).unwrap()),
+        Err(err) => map_storage_error(err),
     }
 }
 
-fn handle_put_request(request: &str, db_url: &str) -> (String, String) {
-    let id_opt = get_id(request).parse::<i32>().ok();
+fn handle_put_request(request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
+    let id_opt = get_id(&request.path).parse::<i32>().ok();
     if id_opt.is_none() {
         return (NOT_FOUND.to_string(), "Invalid ID".to_string());
     }
     let id = id_opt.unwrap();
 
     match get_user_request_body(request) {
-        Ok(user) => {
-            match Client::connect(db_url, NoTls) {
-                Ok(mut client) => {
-                    let res = client.execute(
-                        "UPDATE users SET name = $1, email = $2 WHERE id = $3",
-                        &[&user.name, &user.email, &id],
-                    );
-                    match res {
-                        Ok(affected) if affected > 0 => (OK_RESPONSE.to_string(), "User updated".to_string()),
-                        Ok(_) => (NOT_FOUND.to_string(), "User not found".to_string()),
-                        Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-                    }
-                }
-                Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-            }
-        }
+        Ok(user) => match storage.update_user(id, &user) {
+            Ok(_) => (OK_RESPONSE.to_string(), "User updated".to_string()),
+            Err(err) => map_storage_error(err),
+        },
         Err(_) => (INTERNAL_ERROR.to_string(), "Invalid JSON".to_string()),
     }
 }
 
-fn handle_delete_request(request: &str, db_url: &str) -> (String, String) {
-    let id_opt = get_id(request).parse::<i32>().ok();
+fn handle_delete_request(request: &HttpRequest, storage: &dyn Storage) -> (String, String) {
+    let id_opt = get_id(&request.path).parse::<i32>().ok();
     if id_opt.is_none() {
         return (NOT_FOUND.to_string(), "Invalid ID".to_string());
     }
     let id = id_opt.unwrap();
 
-    match Client::connect(db_url, NoTls) {
-        Ok(mut client) => {
-            let res = client.execute("DELETE FROM users WHERE id = $1", &[&id]);
-            match res {
-                Ok(affected) if affected > 0 => (OK_RESPONSE.to_string(), "User deleted".to_string()),
-                Ok(_) => (NOT_FOUND.to_string(), "User not found".to_string()),
-                Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
-            }
-        }
-        Err(_) => (INTERNAL_ERROR.to_string(), "Internal error".to_string()),
+    match storage.delete_user(id) {
+        Ok(()) => (OK_RESPONSE.to_string(), "User deleted".to_string()),
+        Err(err) => map_storage_error(err),
+    }
+}
+
+fn map_storage_error(err: StorageError) -> (String, String) {
+    match err {
+        StorageError::NotFound => (NOT_FOUND.to_string(), "User not found".to_string()),
+        StorageError::Conflict(msg) => (CONFLICT.to_string(), msg),
+        StorageError::Malformed(msg) => (INTERNAL_ERROR.to_string(), msg),
+        StorageError::Backend(msg) => (INTERNAL_ERROR.to_string(), msg),
     }
 }
 
 fn set_database(db_url: &str) -> Result<(), PostgresError> {
-    let mut client = Client::connect(db_url, NoTls)?;
+    let mut client = tls::connect(db_url)?;
     client.batch_execute(
         "
         CREATE TABLE IF NOT EXISTS users (
             id SERIAL PRIMARY KEY,
             name VARCHAR NOT NULL,
-            email VARCHAR NOT NULL
+            email VARCHAR NOT NULL,
+            password_hash VARCHAR NOT NULL DEFAULT '',
+            salt VARCHAR NOT NULL DEFAULT ''
+        );
+        CREATE TABLE IF NOT EXISTS sessions (
+            token VARCHAR PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            expires_at TIMESTAMPTZ NOT NULL
         )
     ",
     )?;
     Ok(())
 }
 
-fn get_id(request: &str) -> &str {
-    request.split("/").nth(2).unwrap_or_default().split_whitespace().next().unwrap_or_default()
+fn get_id(path: &str) -> &str {
+    path.split('/').nth(2).unwrap_or_default()
+}
+
+fn get_user_request_body(request: &HttpRequest) -> Result<User, serde_json::Error> {
+    serde_json::from_str(&request.body)
+}
+
+fn get_login_request_body(request: &HttpRequest) -> Result<LoginRequest, serde_json::Error> {
+    serde_json::from_str(&request.body)
 }
 
-fn get_user_request_body(request: &str) -> Result<User, serde_json::Error> {
-    serde_json::from_str(request.split("\r\n\r\n").last().unwrap_or_default())
+fn get_users_request_body(request: &HttpRequest) -> Result<Vec<User>, serde_json::Error> {
+    serde_json::from_str(&request.body)
 }
 
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const ROUTES: &[&str] = &[
+    "login",
+    "users_create",
+    "users_batch",
+    "users_get",
+    "users_list",
+    "users_update",
+    "users_delete",
+    "metrics",
+    "not_found",
+];
+
+const DURATION_BUCKETS_MS: &[f64] = &[5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0];
+
+struct Histogram {
+    bucket_counts: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram {
+            bucket_counts: DURATION_BUCKETS_MS.iter().map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        for (bound, bucket) in DURATION_BUCKETS_MS.iter().zip(&self.bucket_counts) {
+            if ms <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct RouteMetrics {
+    status_2xx: AtomicU64,
+    status_4xx: AtomicU64,
+    status_5xx: AtomicU64,
+    duration: Histogram,
+}
+
+impl RouteMetrics {
+    fn new() -> Self {
+        RouteMetrics {
+            status_2xx: AtomicU64::new(0),
+            status_4xx: AtomicU64::new(0),
+            status_5xx: AtomicU64::new(0),
+            duration: Histogram::new(),
+        }
+    }
+
+    fn record(&self, status: u16, duration: Duration) {
+        let bucket = match status {
+            200..=299 => &self.status_2xx,
+            400..=499 => &self.status_4xx,
+            _ => &self.status_5xx,
+        };
+        bucket.fetch_add(1, Ordering::Relaxed);
+        self.duration.observe(duration);
+    }
+}
+
+/// Per-route request counts, response-status counts and a request-duration
+/// histogram, formatted by hand in Prometheus text exposition format so
+/// `GET /metrics` can be scraped without pulling in a metrics crate.
+pub struct Metrics {
+    routes: HashMap<&'static str, RouteMetrics>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            routes: ROUTES.iter().map(|&name| (name, RouteMetrics::new())).collect(),
+        }
+    }
+
+    pub fn record(&self, route: &str, status_line: &str, duration: Duration) {
+        let status = parse_status_code(status_line);
+        if let Some(route_metrics) = self.routes.get(route) {
+            route_metrics.record(status, duration);
+        }
+    }
+
+    pub fn render(&self, pool_connections_in_use: usize) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP http_requests_total Total HTTP requests handled, by route and status class");
+        let _ = writeln!(out, "# TYPE http_requests_total counter");
+        for &route in ROUTES {
+            let m = &self.routes[route];
+            let _ = writeln!(out, "http_requests_total{{route=\"{}\",status=\"2xx\"}} {}", route, m.status_2xx.load(Ordering::Relaxed));
+            let _ = writeln!(out, "http_requests_total{{route=\"{}\",status=\"4xx\"}} {}", route, m.status_4xx.load(Ordering::Relaxed));
+            let _ = writeln!(out, "http_requests_total{{route=\"{}\",status=\"5xx\"}} {}", route, m.status_5xx.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out, "# HELP http_request_duration_milliseconds Request duration in milliseconds, by route");
+        let _ = writeln!(out, "# TYPE http_request_duration_milliseconds histogram");
+        for &route in ROUTES {
+            let m = &self.routes[route];
+            for (bound, bucket) in DURATION_BUCKETS_MS.iter().zip(&m.duration.bucket_counts) {
+                let _ = writeln!(
+                    out,
+                    "http_request_duration_milliseconds_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    route, bound, bucket.load(Ordering::Relaxed)
+                );
+            }
+            let total = m.duration.count.load(Ordering::Relaxed);
+            let _ = writeln!(out, "http_request_duration_milliseconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}", route, total);
+            let sum_ms = m.duration.sum_micros.load(Ordering::Relaxed) as f64 / 1000.0;
+            let _ = writeln!(out, "http_request_duration_milliseconds_sum{{route=\"{}\"}} {}", route, sum_ms);
+            let _ = writeln!(out, "http_request_duration_milliseconds_count{{route=\"{}\"}} {}", route, total);
+        }
+
+        let _ = writeln!(out, "# HELP db_pool_connections_in_use Postgres pool connections currently checked out");
+        let _ = writeln!(out, "# TYPE db_pool_connections_in_use gauge");
+        let _ = writeln!(out, "db_pool_connections_in_use {}", pool_connections_in_use);
+
+        out
+    }
+}
+
+fn parse_status_code(status_line: &str) -> u16 {
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0)
+}
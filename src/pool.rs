@@ -0,0 +1,131 @@
+use crate::tls;
+use postgres::{Client, Error as PostgresError};
+use std::collections::VecDeque;
+use std::env;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex};
+
+const DEFAULT_POOL_SIZE: usize = 8;
+
+/// Bounded pool of Postgres connections, checked out by handlers instead of
+/// reconnecting on every request. Idle connections are kept in `idle`;
+/// `total` tracks how many connections (idle + checked out) currently exist
+/// so we never open more than `max_size` at once.
+pub struct Pool {
+    db_url: String,
+    max_size: usize,
+    idle: Mutex<VecDeque<Client>>,
+    total: Mutex<usize>,
+    available: Condvar,
+}
+
+impl Pool {
+    /// Creates the pool and eagerly opens one connection so a misconfigured
+    /// `DATABASE_URL` is caught at startup rather than on the first request.
+    pub fn new(db_url: &str) -> Result<Pool, PostgresError> {
+        let max_size = env::var("DB_POOL_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_POOL_SIZE);
+
+        let client = tls::connect(db_url)?;
+        let mut idle = VecDeque::with_capacity(max_size);
+        idle.push_back(client);
+
+        Ok(Pool {
+            db_url: db_url.to_string(),
+            max_size,
+            idle: Mutex::new(idle),
+            total: Mutex::new(1),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Checks out a connection, blocking until one is free if the pool is
+    /// already at `max_size`. Connections that were dropped by the server
+    /// (e.g. after an idle timeout) are discarded and replaced transparently.
+    pub fn get(&self) -> Result<PooledClient, PostgresError> {
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(client) = idle.pop_front() {
+                if client.is_closed() {
+                    *self.total.lock().unwrap() -= 1;
+                    self.available.notify_one();
+                    continue;
+                }
+                return Ok(PooledClient {
+                    client: Some(client),
+                    pool: self,
+                });
+            }
+
+            let mut total = self.total.lock().unwrap();
+            if *total < self.max_size {
+                *total += 1;
+                drop(total);
+                drop(idle);
+                let client = match tls::connect(&self.db_url) {
+                    Ok(client) => client,
+                    Err(e) => {
+                        *self.total.lock().unwrap() -= 1;
+                        self.available.notify_one();
+                        return Err(e);
+                    }
+                };
+                return Ok(PooledClient {
+                    client: Some(client),
+                    pool: self,
+                });
+            }
+            drop(total);
+
+            idle = self.available.wait(idle).unwrap();
+        }
+    }
+
+    fn release(&self, client: Client) {
+        self.idle.lock().unwrap().push_back(client);
+        self.available.notify_one();
+    }
+
+    /// Connections currently checked out (for the pool-checkout gauge).
+    pub fn in_use(&self) -> usize {
+        let idle = self.idle.lock().unwrap().len();
+        let total = *self.total.lock().unwrap();
+        total.saturating_sub(idle)
+    }
+}
+
+/// A checked-out connection. Returned to the pool automatically on drop.
+pub struct PooledClient<'a> {
+    client: Option<Client>,
+    pool: &'a Pool,
+}
+
+impl<'a> Deref for PooledClient<'a> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl<'a> DerefMut for PooledClient<'a> {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledClient<'a> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            if !client.is_closed() {
+                self.pool.release(client);
+            } else {
+                *self.pool.total.lock().unwrap() -= 1;
+                self.pool.available.notify_one();
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::auth::{generate_salt, hash_password};
+use crate::pool::Pool;
+use postgres::Error as PostgresError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: Option<i32>,
+    pub name: String,
+    pub email: String,
+    /// Only ever present on the way in (registration); never serialized back out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Backend-agnostic failure modes the HTTP layer can map onto status codes
+/// without knowing anything about Postgres, mirroring the kittybox
+/// `database::ErrorKind` design.
+#[derive(Debug)]
+pub enum StorageError {
+    Backend(String),
+    NotFound,
+    Conflict(String),
+    Malformed(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Backend(msg) => write!(f, "backend error: {}", msg),
+            StorageError::NotFound => write!(f, "not found"),
+            StorageError::Conflict(msg) => write!(f, "conflict: {}", msg),
+            StorageError::Malformed(msg) => write!(f, "malformed input: {}", msg),
+        }
+    }
+}
+
+impl From<PostgresError> for StorageError {
+    fn from(err: PostgresError) -> Self {
+        StorageError::Backend(err.to_string())
+    }
+}
+
+/// Persistence operations the HTTP layer needs, decoupled from any one
+/// backend so handlers stay unit-testable without a running database.
+pub trait Storage: Send + Sync {
+    fn create_user(&self, user: &User) -> Result<User, StorageError>;
+    fn get_user(&self, id: i32) -> Result<User, StorageError>;
+    fn list_users(&self) -> Result<Vec<User>, StorageError>;
+    fn update_user(&self, id: i32, user: &User) -> Result<User, StorageError>;
+    fn delete_user(&self, id: i32) -> Result<(), StorageError>;
+    /// Inserts every user in `users` as a single unit: either all rows land
+    /// or none do. Returns the generated ids in the same order as `users`.
+    fn create_users_batch(&self, users: &[User]) -> Result<Vec<i32>, StorageError>;
+}
+
+/// Storage backed by the pooled Postgres connection, wrapping the queries
+/// that used to live directly in the handlers.
+pub struct PostgresStorage {
+    pool: Arc<Pool>,
+}
+
+impl PostgresStorage {
+    pub fn new(pool: Arc<Pool>) -> Self {
+        PostgresStorage { pool }
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn create_user(&self, user: &User) -> Result<User, StorageError> {
+        let mut client = self.pool.get()?;
+        let salt = generate_salt();
+        let password_hash = user
+            .password
+            .as_deref()
+            .map(|p| hash_password(p, &salt))
+            .unwrap_or_default();
+        let row = client.query_one(
+            "INSERT INTO users (name, email, password_hash, salt) VALUES ($1, $2, $3, $4) RETURNING id, name, email",
+            &[&user.name, &user.email, &password_hash, &salt],
+        )?;
+        Ok(User {
+            id: row.get(0),
+            name: row.get(1),
+            email: row.get(2),
+            password: None,
+        })
+    }
+
+    fn get_user(&self, id: i32) -> Result<User, StorageError> {
+        let mut client = self.pool.get()?;
+        let row = client.query_opt("SELECT id, name, email FROM users WHERE id = $1", &[&id])?;
+        match row {
+            Some(row) => Ok(User {
+                id: row.get(0),
+                name: row.get(1),
+                email: row.get(2),
+                password: None,
+            }),
+            None => Err(StorageError::NotFound),
+        }
+    }
+
+    fn list_users(&self) -> Result<Vec<User>, StorageError> {
+        let mut client = self.pool.get()?;
+        let rows = client.query("SELECT id, name, email FROM users", &[])?;
+        Ok(rows
+            .iter()
+            .map(|row| User {
+                id: row.get(0),
+                name: row.get(1),
+                email: row.get(2),
+                password: None,
+            })
+            .collect())
+    }
+
+    fn update_user(&self, id: i32, user: &User) -> Result<User, StorageError> {
+        let mut client = self.pool.get()?;
+        let affected = client.execute(
+            "UPDATE users SET name = $1, email = $2 WHERE id = $3",
+            &[&user.name, &user.email, &id],
+        )?;
+        if affected == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(User {
+            id: Some(id),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            password: None,
+        })
+    }
+
+    fn delete_user(&self, id: i32) -> Result<(), StorageError> {
+        let mut client = self.pool.get()?;
+        let affected = client.execute("DELETE FROM users WHERE id = $1", &[&id])?;
+        if affected == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn create_users_batch(&self, users: &[User]) -> Result<Vec<i32>, StorageError> {
+        let mut client = self.pool.get()?;
+        let mut transaction = client.transaction()?;
+
+        let mut ids = Vec::with_capacity(users.len());
+        for user in users {
+            let salt = generate_salt();
+            let password_hash = user
+                .password
+                .as_deref()
+                .map(|p| hash_password(p, &salt))
+                .unwrap_or_default();
+            match transaction.query_one(
+                "INSERT INTO users (name, email, password_hash, salt) VALUES ($1, $2, $3, $4) RETURNING id",
+                &[&user.name, &user.email, &password_hash, &salt],
+            ) {
+                Ok(row) => ids.push(row.get(0)),
+                Err(err) => {
+                    let _ = transaction.rollback();
+                    return Err(StorageError::from(err));
+                }
+            }
+        }
+
+        transaction.commit()?;
+        Ok(ids)
+    }
+}
+
+/// In-memory backend for tests and local dev with no database available.
+pub struct InMemoryStorage {
+    users: Mutex<HashMap<i32, User>>,
+    next_id: Mutex<i32>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        InMemoryStorage {
+            users: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(1),
+        }
+    }
+}
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for InMemoryStorage {
+    fn create_user(&self, user: &User) -> Result<User, StorageError> {
+        let mut next_id = self.next_id.lock().unwrap();
+        let id = *next_id;
+        *next_id += 1;
+        let stored = User {
+            id: Some(id),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            password: None,
+        };
+        self.users.lock().unwrap().insert(id, stored.clone());
+        Ok(stored)
+    }
+
+    fn get_user(&self, id: i32) -> Result<User, StorageError> {
+        self.users
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(StorageError::NotFound)
+    }
+
+    fn list_users(&self) -> Result<Vec<User>, StorageError> {
+        Ok(self.users.lock().unwrap().values().cloned().collect())
+    }
+
+    fn update_user(&self, id: i32, user: &User) -> Result<User, StorageError> {
+        let mut users = self.users.lock().unwrap();
+        if !users.contains_key(&id) {
+            return Err(StorageError::NotFound);
+        }
+        let stored = User {
+            id: Some(id),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            password: None,
+        };
+        users.insert(id, stored.clone());
+        Ok(stored)
+    }
+
+    fn delete_user(&self, id: i32) -> Result<(), StorageError> {
+        let mut users = self.users.lock().unwrap();
+        if users.remove(&id).is_none() {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn create_users_batch(&self, users: &[User]) -> Result<Vec<i32>, StorageError> {
+        users
+            .iter()
+            .map(|user| self.create_user(user).map(|created| created.id.unwrap()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str, email: &str) -> User {
+        User {
+            id: None,
+            name: name.to_string(),
+            email: email.to_string(),
+            password: None,
+        }
+    }
+
+    #[test]
+    fn create_assigns_increasing_ids() {
+        let storage = InMemoryStorage::new();
+        let first = storage.create_user(&user("Alice", "alice@example.com")).unwrap();
+        let second = storage.create_user(&user("Bob", "bob@example.com")).unwrap();
+        assert_eq!(first.id, Some(1));
+        assert_eq!(second.id, Some(2));
+    }
+
+    #[test]
+    fn get_roundtrips_a_created_user() {
+        let storage = InMemoryStorage::new();
+        let created = storage.create_user(&user("Alice", "alice@example.com")).unwrap();
+        let fetched = storage.get_user(created.id.unwrap()).unwrap();
+        assert_eq!(fetched.name, "Alice");
+        assert_eq!(fetched.email, "alice@example.com");
+    }
+
+    #[test]
+    fn get_missing_user_is_not_found() {
+        let storage = InMemoryStorage::new();
+        assert!(matches!(storage.get_user(1), Err(StorageError::NotFound)));
+    }
+
+    #[test]
+    fn list_returns_every_created_user() {
+        let storage = InMemoryStorage::new();
+        storage.create_user(&user("Alice", "alice@example.com")).unwrap();
+        storage.create_user(&user("Bob", "bob@example.com")).unwrap();
+        assert_eq!(storage.list_users().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn update_replaces_name_and_email() {
+        let storage = InMemoryStorage::new();
+        let created = storage.create_user(&user("Alice", "alice@example.com")).unwrap();
+        let updated = storage
+            .update_user(created.id.unwrap(), &user("Alicia", "alicia@example.com"))
+            .unwrap();
+        assert_eq!(updated.name, "Alicia");
+        assert_eq!(updated.email, "alicia@example.com");
+    }
+
+    #[test]
+    fn update_missing_user_is_not_found() {
+        let storage = InMemoryStorage::new();
+        assert!(matches!(
+            storage.update_user(1, &user("Alice", "alice@example.com")),
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn delete_removes_the_user() {
+        let storage = InMemoryStorage::new();
+        let created = storage.create_user(&user("Alice", "alice@example.com")).unwrap();
+        storage.delete_user(created.id.unwrap()).unwrap();
+        assert!(matches!(
+            storage.get_user(created.id.unwrap()),
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn delete_missing_user_is_not_found() {
+        let storage = InMemoryStorage::new();
+        assert!(matches!(storage.delete_user(1), Err(StorageError::NotFound)));
+    }
+
+    #[test]
+    fn batch_create_assigns_an_id_per_user() {
+        let storage = InMemoryStorage::new();
+        let ids = storage
+            .create_users_batch(&[
+                user("Alice", "alice@example.com"),
+                user("Bob", "bob@example.com"),
+            ])
+            .unwrap();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(storage.list_users().unwrap().len(), 2);
+    }
+}
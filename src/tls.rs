@@ -0,0 +1,51 @@
+use native_tls::{Certificate, TlsConnector};
+use postgres::{Client, Error as PostgresError, NoTls};
+use postgres_native_tls::MakeTlsConnector;
+use std::env;
+use std::fs;
+
+/// Mirrors libpq's `sslmode`, restricted to the cases this project supports.
+enum SslMode {
+    Disable,
+    Require,
+    VerifyFull,
+}
+
+impl SslMode {
+    fn from_env() -> SslMode {
+        match env::var("DATABASE_SSLMODE").ok().as_deref() {
+            Some("require") => SslMode::Require,
+            Some("verify-full") => SslMode::VerifyFull,
+            _ => SslMode::Disable,
+        }
+    }
+}
+
+/// Connects to Postgres using `DATABASE_SSLMODE` (default `disable`) to pick
+/// between a plain connection and one encrypted with `postgres-native-tls`.
+/// This is the single place `Client::connect` should be called from.
+pub fn connect(db_url: &str) -> Result<Client, PostgresError> {
+    match SslMode::from_env() {
+        SslMode::Disable => Client::connect(db_url, NoTls),
+        mode => Client::connect(db_url, build_connector(mode)),
+    }
+}
+
+fn build_connector(mode: SslMode) -> MakeTlsConnector {
+    let mut builder = TlsConnector::builder();
+
+    if let SslMode::Require = mode {
+        // "require" only asks for encryption in transit, not a verified chain.
+        builder.danger_accept_invalid_certs(true);
+        builder.danger_accept_invalid_hostnames(true);
+    }
+
+    if let Ok(ca_path) = env::var("DATABASE_CA_CERT") {
+        let pem = fs::read(&ca_path).expect("failed to read DATABASE_CA_CERT");
+        let cert = Certificate::from_pem(&pem).expect("invalid CA certificate in DATABASE_CA_CERT");
+        builder.add_root_certificate(cert);
+    }
+
+    let connector = builder.build().expect("failed to build TLS connector");
+    MakeTlsConnector::new(connector)
+}